@@ -4,83 +4,414 @@ use std::path::Path;
 use std::str::FromStr;
 
 use image::codecs::png::PngEncoder;
+use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
 use image::{ColorType, ImageEncoder};
 use num_complex::Complex;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 
 struct AppArgs {
     target_file_name: Box<Path>,
     bounds: (usize, usize),
     upper_left: Complex<f64>,
     lower_right: Complex<f64>,
+    fractal: FractalKind,
+    buddhabrot: bool,
+    color: Palette,
+    format: OutputFormat,
+    threads: Option<usize>,
+    max_iter: usize,
+}
+
+enum OutputFormat {
+    Png,
+    /// Binary portable pixmap (`P6`) — 24-bit RGB.
+    Ppm,
+    /// Binary portable graymap (`P5`) — 8-bit grayscale.
+    Pgm,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "png" => Ok(OutputFormat::Png),
+            "ppm" => Ok(OutputFormat::Ppm),
+            "pgm" => Ok(OutputFormat::Pgm),
+            _ => Err(format!("Unknown output format: {}", value)),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Infer the format from a target file extension, defaulting to PNG.
+    fn from_path(path: &Path) -> OutputFormat {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| OutputFormat::from_str(ext).ok())
+            .unwrap_or(OutputFormat::Png)
+    }
+}
+
+enum Palette {
+    Grayscale,
+    Fire,
+    Hsv,
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "grayscale" => Ok(Palette::Grayscale),
+            "fire" => Ok(Palette::Fire),
+            "hsv" => Ok(Palette::Hsv),
+            _ => Err(format!("Unknown palette: {}", value)),
+        }
+    }
+}
+
+impl Palette {
+    /// Map a (fractional) escape-time `count` (or `None` for points in the set)
+    /// to an RGB triple. `limit` is the maximum iteration count used as the
+    /// color scale, so the mapping is continuous and depth-independent.
+    fn color(&self, count: Option<f64>, limit: usize) -> [u8; 3] {
+        let t = match count {
+            None => return [0, 0, 0],
+            Some(count) => (count / limit as f64).clamp(0.0, 1.0),
+        };
+
+        match self {
+            Palette::Grayscale => {
+                let value = ((1.0 - t) * 255.0).round() as u8;
+                [value, value, value]
+            }
+            Palette::Fire => [
+                (t * 3.0).min(1.0),
+                (t * 3.0 - 1.0).clamp(0.0, 1.0),
+                (t * 3.0 - 2.0).clamp(0.0, 1.0),
+            ]
+            .map(|channel| (channel * 255.0).round() as u8),
+            Palette::Hsv => hsv_to_rgb(360.0 * t, 1.0, 1.0),
+        }
+    }
+}
+
+/// Convert an HSV color (`hue` in degrees, `saturation`/`value` in `0..=1`) to
+/// an 8-bit RGB triple.
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> [u8; 3] {
+    let chroma = value * saturation;
+    let hue_prime = (hue / 60.0) % 6.0;
+    let x = chroma * (1.0 - (hue_prime % 2.0 - 1.0).abs());
+    let m = value - chroma;
+
+    let (r, g, b) = match hue_prime as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    [r, g, b].map(|channel| ((channel + m) * 255.0).round() as u8)
+}
+
+enum FractalKind {
+    Mandelbrot,
+    Mandelbrot3,
+    BurningShip,
+}
+
+impl FromStr for FractalKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "mandelbrot" => Ok(FractalKind::Mandelbrot),
+            "mandelbrot3" => Ok(FractalKind::Mandelbrot3),
+            "burning_ship" => Ok(FractalKind::BurningShip),
+            _ => Err(format!("Unknown fractal kind: {}", value)),
+        }
+    }
+}
+
+impl FractalKind {
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        match self {
+            FractalKind::Mandelbrot => z * z + c,
+            FractalKind::Mandelbrot3 => z * z * z + c,
+            FractalKind::BurningShip => {
+                let z = Complex {
+                    re: z.re.abs(),
+                    im: z.im.abs(),
+                };
+                z * z + c
+            }
+        }
+    }
 }
 
 fn main() {
     let app_args: AppArgs = parse_app_args();
-    let pixels = render_concurrent(app_args.bounds, app_args.upper_left, app_args.lower_right);
+    let pixels = if app_args.buddhabrot {
+        render_buddhabrot(
+            app_args.bounds,
+            app_args.upper_left,
+            app_args.lower_right,
+            &app_args.fractal,
+        )
+    } else {
+        render_concurrent(
+            app_args.bounds,
+            app_args.upper_left,
+            app_args.lower_right,
+            &app_args.fractal,
+            &app_args.color,
+            app_args.threads,
+            app_args.max_iter,
+        )
+    };
 
-    write_image(&app_args.target_file_name, &pixels, app_args.bounds)
-        .expect("Can't save result image.");
+    write_image(
+        &app_args.target_file_name,
+        &pixels,
+        app_args.bounds,
+        &app_args.format,
+    )
+    .expect("Can't save result image.");
 }
 
 fn parse_app_args() -> AppArgs {
-    let args: Vec<String> = args().skip(1).collect();
-    if args.len() != 4 {
-        eprintln!("Need 4 arguments: <TARGET_FILE_NAME_PNG> <BOUNDS> <UPPER_LEFT_COMPLEX_NUM_POINT> <LOWER_RIGHT_COMPLEX_NUM_POINT>");
+    let all_args: Vec<String> = args().skip(1).collect();
+    let buddhabrot = all_args.iter().any(|arg| arg == "--buddhabrot");
+    let color = parse_option_value(&all_args, "--color")
+        .map(|value| Palette::from_str(&value).expect("Can't parse palette!"))
+        .unwrap_or(Palette::Grayscale);
+    let explicit_format = parse_option_value(&all_args, "--format")
+        .map(|value| OutputFormat::from_str(&value).expect("Can't parse output format!"));
+    let threads = parse_option_value(&all_args, "--threads")
+        .map(|value| value.parse::<usize>().expect("Can't parse thread count!"));
+    let max_iter = parse_option_value(&all_args, "--max-iter")
+        .map(|value| value.parse::<usize>().expect("Can't parse max iteration count!"))
+        .unwrap_or(255);
+    let positional = positional_args(&all_args);
+
+    if positional.len() != 5 {
+        eprintln!("Need 5 arguments: <TARGET_FILE_NAME> <BOUNDS> <UPPER_LEFT_COMPLEX_NUM_POINT> <LOWER_RIGHT_COMPLEX_NUM_POINT> <FRACTAL_KIND> [--buddhabrot]");
         std::process::exit(1);
     }
 
-    let target_file_name = Path::new(args[0].clone().as_str())
+    let target_file_name = Path::new(positional[0].clone().as_str())
         .to_owned()
         .into_boxed_path();
-    let bounds = parse_pair::<usize>(&args[1].clone(), 'x').expect("Can't parse bounds!"); // 1024x768
-    let upper_left = parse_complex(&args[2].clone()).expect("Can't parse upper left point!"); // -1.0,1.0
-    let lower_right = parse_complex(&args[3].clone()).expect("Can't parse lower right point!"); // 1.0,-1.0
+    let format = explicit_format.unwrap_or_else(|| OutputFormat::from_path(&target_file_name));
+    let bounds = parse_pair::<usize>(&positional[1].clone(), 'x').expect("Can't parse bounds!"); // 1024x768
+    let upper_left = parse_complex(&positional[2].clone()).expect("Can't parse upper left point!"); // -1.0,1.0
+    let lower_right =
+        parse_complex(&positional[3].clone()).expect("Can't parse lower right point!"); // 1.0,-1.0
+    let fractal =
+        FractalKind::from_str(&positional[4].clone()).expect("Can't parse fractal kind!"); // mandelbrot
 
     AppArgs {
         target_file_name,
         bounds,
         upper_left,
         lower_right,
+        fractal,
+        buddhabrot,
+        color,
+        format,
+        threads,
+        max_iter,
     }
 }
 
+/// Collect the positional arguments, skipping `--flag` switches and the value
+/// immediately following any value-taking flag (e.g. `--color fire`).
+fn positional_args(all_args: &[String]) -> Vec<String> {
+    let value_flags = ["--color", "--format", "--threads", "--max-iter"];
+    let mut positional = Vec::new();
+    let mut iter = all_args.iter();
+    while let Some(arg) = iter.next() {
+        if value_flags.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with("--") {
+            positional.push(arg.clone());
+        }
+    }
+    positional
+}
+
+/// Return the value following `flag` in `all_args`, if present.
+fn parse_option_value(all_args: &[String], flag: &str) -> Option<String> {
+    all_args
+        .iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| all_args.get(index + 1))
+        .cloned()
+}
+
 fn render_concurrent(
     bounds: (usize, usize),
     upper_left: Complex<f64>,
     lower_right: Complex<f64>,
+    fractal: &FractalKind,
+    color: &Palette,
+    threads: Option<usize>,
+    max_iter: usize,
 ) -> Vec<u8> {
-    let mut pixels = vec![0; bounds.0 * bounds.1];
-
-    let threads = 8;
-    let rows_per_band = bounds.1 / threads + 1;
-    let bands: Vec<&mut [u8]> = pixels.chunks_mut(rows_per_band * bounds.0).collect();
-
-    crossbeam::scope(|spawner| {
-        for (i, band) in bands.into_iter().enumerate() {
-            let top = rows_per_band * i;
-            let height = band.len() / bounds.0;
-            let band_bounds = (bounds.0, height);
-            let band_upper_left = pixel_to_point(bounds, (0, top), upper_left, lower_right);
-            let band_lower_right =
-                pixel_to_point(bounds, (bounds.0, top + height), upper_left, lower_right);
-
-            spawner.spawn(move |_| {
-                render(band_bounds, band, band_upper_left, band_lower_right);
+    let mut pixels = vec![0; bounds.0 * bounds.1 * 3];
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(thread_count(threads))
+        .build()
+        .expect("Can't build thread pool!");
+
+    // Parallelize per row with work stealing, so deep-in-the-set rows that cost
+    // far more iterations don't starve the cheap rows on a fixed band split.
+    pool.install(|| {
+        pixels
+            .par_chunks_mut(bounds.0 * 3)
+            .enumerate()
+            .for_each(|(row, band)| {
+                let row_upper_left = pixel_to_point(bounds, (0, row), upper_left, lower_right);
+                let row_lower_right =
+                    pixel_to_point(bounds, (bounds.0, row + 1), upper_left, lower_right);
+                render(
+                    (bounds.0, 1),
+                    band,
+                    row_upper_left,
+                    row_lower_right,
+                    fractal,
+                    color,
+                    max_iter,
+                );
             });
-        }
-    })
-    .unwrap();
+    });
 
     pixels
 }
 
-fn escape_time(c: Complex<f64>, limit: usize) -> Option<usize> {
+/// Resolve the requested thread count, falling back to the machine's available
+/// parallelism (and finally to a single thread) when none was supplied.
+fn thread_count(threads: Option<usize>) -> usize {
+    threads.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    })
+}
+
+fn render_buddhabrot(
+    bounds: (usize, usize),
+    upper_left: Complex<f64>,
+    lower_right: Complex<f64>,
+    fractal: &FractalKind,
+) -> Vec<u8> {
+    let limit = 255;
+    let samples_per_pixel = 16;
+
+    let mut density = vec![0u32; bounds.0 * bounds.1];
+    let mut trajectory: Vec<Complex<f64>> = Vec::with_capacity(limit);
+
+    // Iterate a grid finer than `bounds` with a deterministic sub-pixel jitter,
+    // so each run produces the same image without pulling in an rng dependency.
+    let mut seed: u64 = 0x9e3779b97f4a7c15;
+    for row in 0..bounds.1 {
+        for col in 0..bounds.0 {
+            for _ in 0..samples_per_pixel {
+                let (jitter_x, jitter_y) = next_jitter(&mut seed);
+                let sample = (col as f64 + jitter_x, row as f64 + jitter_y);
+                let c = sub_pixel_to_point(bounds, sample, upper_left, lower_right);
+
+                if let Some(escaped) = record_orbit(c, limit, fractal, &mut trajectory) {
+                    for &z in &trajectory[..escaped] {
+                        if let Some((px, py)) =
+                            point_to_pixel(bounds, z, upper_left, lower_right)
+                        {
+                            density[py * bounds.0 + px] += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    normalize_density(&density)
+}
+
+/// Run the escape-time iteration for `c`, recording every intermediate `z`
+/// into `trajectory`. Returns the number of recorded points if the orbit
+/// escaped (`norm_sqr > 4.0`) within `limit`, or `None` if it stayed bounded.
+fn record_orbit(
+    c: Complex<f64>,
+    limit: usize,
+    fractal: &FractalKind,
+    trajectory: &mut Vec<Complex<f64>>,
+) -> Option<usize> {
+    trajectory.clear();
     let mut z = Complex { re: 0.0, im: 0.0 };
+    for _ in 0..limit {
+        z = fractal.step(z, c);
+        trajectory.push(z);
+        if z.norm_sqr() > 4.0 {
+            return Some(trajectory.len());
+        }
+    }
+
+    None
+}
+
+/// Map a density histogram to an 8-bit buffer, applying a gamma curve so the
+/// faint tails of the accumulation remain visible against the bright core.
+fn normalize_density(density: &[u32]) -> Vec<u8> {
+    let max = density.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return vec![0; density.len() * 3];
+    }
+
+    let max = max as f64;
+    let mut pixels = Vec::with_capacity(density.len() * 3);
+    for &count in density {
+        let normalized = (count as f64 / max).powf(1.0 / 2.2);
+        let value = (normalized * 255.0).round() as u8;
+        pixels.extend_from_slice(&[value, value, value]);
+    }
+    pixels
+}
+
+/// Advance a small LCG and return a jitter offset in `[0.0, 1.0)` per axis.
+fn next_jitter(seed: &mut u64) -> (f64, f64) {
+    let x = lcg(seed);
+    let y = lcg(seed);
+    (
+        (x as f64) / (u32::MAX as f64 + 1.0),
+        (y as f64) / (u32::MAX as f64 + 1.0),
+    )
+}
+
+fn lcg(seed: &mut u64) -> u32 {
+    *seed = seed
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    (*seed >> 32) as u32
+}
+
+/// Return the smooth (fractional) escape time of `c`, or `None` if the orbit
+/// stays bounded within `limit`. The continuous count
+/// `mu = i + 1 - ln(ln(|z|)) / ln(2)` removes the stair-step banding that an
+/// integer iteration count produces.
+fn escape_time(c: Complex<f64>, limit: usize, fractal: &FractalKind) -> Option<f64> {
+    let mut z: Complex<f64> = Complex { re: 0.0, im: 0.0 };
     for i in 0..limit {
         if z.norm_sqr() > 8.0 {
-            return Some(i);
+            let mu = i as f64 + 1.0 - z.norm().ln().ln() / 2.0_f64.ln();
+            return Some(mu);
         }
-        z = z * z + c;
+        z = fractal.step(z, c);
     }
 
     None
@@ -90,32 +421,64 @@ fn write_image(
     filename: &Path,
     pixels: &[u8],
     bounds: (usize, usize),
+    format: &OutputFormat,
 ) -> Result<(), std::io::Error> {
     let output = File::create(filename)?;
-    let encoder = PngEncoder::new(output);
-    encoder
-        .write_image(pixels, bounds.0 as u32, bounds.1 as u32, ColorType::L8)
-        .expect("Could not write PNG file");
+    let (width, height) = (bounds.0 as u32, bounds.1 as u32);
+
+    match format {
+        OutputFormat::Png => {
+            PngEncoder::new(output)
+                .write_image(pixels, width, height, ColorType::Rgb8)
+                .expect("Could not write PNG file");
+        }
+        OutputFormat::Ppm => {
+            PnmEncoder::new(output)
+                .with_subtype(PnmSubtype::Pixmap(SampleEncoding::Binary))
+                .write_image(pixels, width, height, ColorType::Rgb8)
+                .expect("Could not write PPM file");
+        }
+        OutputFormat::Pgm => {
+            let luma = rgb_to_luma(pixels);
+            PnmEncoder::new(output)
+                .with_subtype(PnmSubtype::Graymap(SampleEncoding::Binary))
+                .write_image(&luma, width, height, ColorType::L8)
+                .expect("Could not write PGM file");
+        }
+    }
 
     Ok(())
 }
 
+/// Flatten an RGB buffer to 8-bit luma using the Rec. 601 luminance weights.
+fn rgb_to_luma(pixels: &[u8]) -> Vec<u8> {
+    pixels
+        .chunks_exact(3)
+        .map(|rgb| {
+            let [r, g, b] = [rgb[0] as f64, rgb[1] as f64, rgb[2] as f64];
+            (0.299 * r + 0.587 * g + 0.114 * b).round() as u8
+        })
+        .collect()
+}
+
 fn render(
     bounds: (usize, usize),
     pixel: &mut [u8],
     upper_left_corner: Complex<f64>,
     lower_right_corner: Complex<f64>,
+    fractal: &FractalKind,
+    color: &Palette,
+    max_iter: usize,
 ) {
-    assert_eq!(pixel.len(), bounds.0 * bounds.1);
+    assert_eq!(pixel.len(), bounds.0 * bounds.1 * 3);
 
     for row in 0..bounds.1 {
         for col in 0..bounds.0 {
             let point = pixel_to_point(bounds, (col, row), upper_left_corner, lower_right_corner);
 
-            pixel[row * bounds.0 + col] = match escape_time(point, 255) {
-                None => 0,
-                Some(count) => 255 - count as u8,
-            }
+            let rgb = color.color(escape_time(point, max_iter, fractal), max_iter);
+            pixel[(row * bounds.0 + col) * 3..(row * bounds.0 + col) * 3 + 3]
+                .copy_from_slice(&rgb);
         }
     }
 }
@@ -137,6 +500,51 @@ fn pixel_to_point(
     }
 }
 
+fn sub_pixel_to_point(
+    bounds: (usize, usize),
+    pixel: (f64, f64),
+    upper_left_corner: Complex<f64>,
+    lower_right_corner: Complex<f64>,
+) -> Complex<f64> {
+    let (width, height) = (
+        lower_right_corner.re - upper_left_corner.re,
+        upper_left_corner.im - lower_right_corner.im,
+    );
+
+    Complex {
+        re: upper_left_corner.re + (pixel.0 * (width / bounds.0 as f64)),
+        im: upper_left_corner.im - (pixel.1 * (height / bounds.1 as f64)),
+    }
+}
+
+/// Inverse of [`pixel_to_point`]: map a complex point back to its pixel cell,
+/// returning `None` when the point falls outside the rendered window.
+fn point_to_pixel(
+    bounds: (usize, usize),
+    point: Complex<f64>,
+    upper_left_corner: Complex<f64>,
+    lower_right_corner: Complex<f64>,
+) -> Option<(usize, usize)> {
+    let (width, height) = (
+        lower_right_corner.re - upper_left_corner.re,
+        upper_left_corner.im - lower_right_corner.im,
+    );
+
+    let col = (point.re - upper_left_corner.re) / (width / bounds.0 as f64);
+    let row = (upper_left_corner.im - point.im) / (height / bounds.1 as f64);
+
+    if col < 0.0 || row < 0.0 {
+        return None;
+    }
+
+    let (col, row) = (col as usize, row as usize);
+    if col < bounds.0 && row < bounds.1 {
+        Some((col, row))
+    } else {
+        None
+    }
+}
+
 fn parse_pair<T: FromStr>(value: &str, separator: char) -> Option<(T, T)> {
     match value.find(separator) {
         None => None,
@@ -186,6 +594,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_fractal_kind() {
+        assert!(matches!(
+            FractalKind::from_str("mandelbrot"),
+            Ok(FractalKind::Mandelbrot)
+        ));
+        assert!(matches!(
+            FractalKind::from_str("mandelbrot3"),
+            Ok(FractalKind::Mandelbrot3)
+        ));
+        assert!(matches!(
+            FractalKind::from_str("burning_ship"),
+            Ok(FractalKind::BurningShip)
+        ));
+        assert!(FractalKind::from_str("julia").is_err());
+    }
+
+    #[test]
+    fn test_point_to_pixel() {
+        let bounds = (100, 200);
+        let upper_left = Complex { re: -1.0, im: 1.0 };
+        let lower_right = Complex { re: 1.0, im: -1.0 };
+
+        let point = pixel_to_point(bounds, (25, 75), upper_left, lower_right);
+        assert_eq!(
+            point_to_pixel(bounds, point, upper_left, lower_right),
+            Some((25, 75))
+        );
+
+        assert_eq!(
+            point_to_pixel(bounds, Complex { re: 5.0, im: 5.0 }, upper_left, lower_right),
+            None
+        );
+    }
+
+    #[test]
+    fn test_palette_color() {
+        // Points inside the set are always black.
+        assert_eq!(Palette::Hsv.color(None, 255), [0, 0, 0]);
+        assert_eq!(Palette::Grayscale.color(None, 255), [0, 0, 0]);
+
+        // Grayscale keeps the original escape-time ramp on all channels.
+        assert_eq!(Palette::Grayscale.color(Some(10.0), 255), [245, 245, 245]);
+
+        // Hue 0 is pure red.
+        assert_eq!(Palette::Hsv.color(Some(0.0), 255), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_output_format_from_path() {
+        assert!(matches!(
+            OutputFormat::from_path(Path::new("out.ppm")),
+            OutputFormat::Ppm
+        ));
+        assert!(matches!(
+            OutputFormat::from_path(Path::new("out.pgm")),
+            OutputFormat::Pgm
+        ));
+        assert!(matches!(
+            OutputFormat::from_path(Path::new("out.png")),
+            OutputFormat::Png
+        ));
+        // Unknown or missing extensions fall back to PNG.
+        assert!(matches!(
+            OutputFormat::from_path(Path::new("out.bmp")),
+            OutputFormat::Png
+        ));
+    }
+
     #[test]
     fn test_parse_pair() {
         assert_eq!(parse_pair::<u32>("", ','), None);